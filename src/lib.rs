@@ -4,69 +4,349 @@ use rayon::prelude::*;
 use crossbeam_channel::Sender;
 use lru::LruCache;
 use std::num::NonZeroUsize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
-/// 缓存结构体
-struct CombinationCache {
-    cache: Mutex<LruCache<String, Vec<Vec<f64>>>>,
+/// 结果缓存，按完整问题实例键入，容量/开关可通过`configure_cache`运行时调整
+struct CombinationCache<T: Clone> {
+    cache: Mutex<LruCache<u64, T>>,
+    enabled: AtomicBool,
 }
 
-impl CombinationCache {
-    fn new(capacity: usize) -> Self {
-        let cap = NonZeroUsize::new(capacity).unwrap();
+impl<T: Clone> CombinationCache<T> {
+    fn new(capacity: usize, enabled: bool) -> Self {
+        let cap = NonZeroUsize::new(capacity.max(1)).unwrap();
         CombinationCache {
             cache: Mutex::new(LruCache::new(cap)),
+            enabled: AtomicBool::new(enabled),
         }
     }
 
-    fn get(&self, target: f64, tolerance: f64) -> Option<Vec<Vec<f64>>> {
-        let key = format!("{:.2}_{:.2}", target, tolerance);
+    fn configure(&self, capacity: usize, enabled: bool) {
+        let cap = NonZeroUsize::new(capacity.max(1)).unwrap();
+        self.cache.lock().unwrap().resize(cap);
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    fn get(&self, key: u64) -> Option<T> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return None;
+        }
         self.cache.lock().unwrap().get(&key).cloned()
     }
 
-    fn put(&self, target: f64, tolerance: f64, results: Vec<Vec<f64>>) {
-        let key = format!("{:.2}_{:.2}", target, tolerance);
+    fn put(&self, key: u64, results: T) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
         self.cache.lock().unwrap().put(key, results);
     }
 }
 
-/// 查找数字组合的解
+// 默认容量100万条(与重新键入前保持一致)，默认开启；可通过`configure_cache`调整
+static CACHE: once_cell::sync::Lazy<CombinationCache<Vec<Vec<f64>>>> = once_cell::sync::Lazy::new(|| {
+    CombinationCache::new(1_000_000, true)
+});
+
+/// `find_combinations_labeled`的返回类型别名，避免触发`clippy::type_complexity`
+type LabeledCombinations = Vec<Vec<(String, f64)>>;
+
+// `find_combinations_labeled`专用的缓存，键入方式额外包含标签，见`cache_key_labeled`
+static LABELED_CACHE: once_cell::sync::Lazy<CombinationCache<LabeledCombinations>> =
+    once_cell::sync::Lazy::new(|| CombinationCache::new(1_000_000, true));
+
+/// 调整结果缓存的容量与开关状态(`find_combinations`与`find_combinations_labeled`共用)
+pub fn configure_cache(capacity: usize, enabled: bool) {
+    CACHE.configure(capacity, enabled);
+    LABELED_CACHE.configure(capacity, enabled);
+}
+
+/// 将完整问题实例哈希为稳定的缓存键，避免不同输入/参数意外命中彼此的缓存结果
+fn cache_key(
+    sorted_numbers: &[f64],
+    target: f64,
+    tolerance: f64,
+    max_length: usize,
+    max_results: usize,
+    mode: &str,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    sorted_numbers.len().hash(&mut hasher);
+    for n in sorted_numbers {
+        n.to_bits().hash(&mut hasher);
+    }
+    target.to_bits().hash(&mut hasher);
+    tolerance.to_bits().hash(&mut hasher);
+    max_length.hash(&mut hasher);
+    max_results.hash(&mut hasher);
+    mode.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 同[`cache_key`]，但把标签也哈希进去，避免数值相同、标签不同的输入互相命中缓存
+fn cache_key_labeled(
+    sorted_items: &[(String, f64)],
+    target: f64,
+    tolerance: f64,
+    max_length: usize,
+    max_results: usize,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    sorted_items.len().hash(&mut hasher);
+    for (label, value) in sorted_items {
+        label.hash(&mut hasher);
+        value.to_bits().hash(&mut hasher);
+    }
+    target.to_bits().hash(&mut hasher);
+    tolerance.to_bits().hash(&mut hasher);
+    max_length.hash(&mut hasher);
+    max_results.hash(&mut hasher);
+    "mitm_labeled".hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 查找数字组合的解(默认使用meet-in-the-middle算法，可处理40~50个元素的输入)
 pub fn find_combinations(
     numbers: &[f64],
     target: f64,
     tolerance: f64,
     progress_tx: Option<crossbeam_channel::Sender<f64>>,
     max_length: usize,
+    max_results: usize,
     stop_flag: Arc<AtomicBool>,
 ) -> Vec<Vec<f64>> {
-    // 初始化缓存(1GB容量，约可存储100万条记录)
-    static CACHE: once_cell::sync::Lazy<CombinationCache> = once_cell::sync::Lazy::new(|| {
-        CombinationCache::new(1_000_000)
-    });
+    println!("输入数字: {:?}", numbers);
+    println!("目标和: {}, 误差: {}, 最大长度: {}", target, tolerance, max_length);
+
+    // 先排序数字以便更高效搜索，排序后的数组也用于生成缓存键
+    let mut sorted_numbers = numbers.to_vec();
+    sorted_numbers.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    println!("排序后数字: {:?}", sorted_numbers);
+
+    let key = cache_key(&sorted_numbers, target, tolerance, max_length, max_results, "mitm");
 
     // 检查缓存
-    if let Some(cached) = CACHE.get(target, tolerance) {
+    if let Some(cached) = CACHE.get(key) {
         println!("从缓存中找到结果");
         return cached;
     }
 
-    println!("输入数字: {:?}", numbers);
-    println!("目标和: {}, 误差: {}, 最大长度: {}", target, tolerance, max_length);
-    
-    let results = Arc::new(Mutex::new(Vec::<Vec<f64>>::new()));
-    let total = numbers.len();
-    let max_results = 1000; // 限制最大结果数量
-    let max_length = max_length; // 使用传入的参数值
-    
-    // 先排序数字以便更高效搜索
-    let mut sorted_numbers = numbers.to_vec();
-    sorted_numbers.sort_by(|a, b| a.partial_cmp(b).unwrap());
-    println!("排序后数字: {:?}", sorted_numbers);
+    let final_results = find_combinations_mitm(
+        &sorted_numbers, target, tolerance, max_length, max_results, &stop_flag, progress_tx.as_ref(),
+    );
+
+    // 存入缓存
+    CACHE.put(key, final_results.clone());
+    final_results
+}
+
+/// 同`find_combinations`，但为每个数值保留标签(如CSV行标识)，便于对账时
+/// 看出是*哪几行*命中了目标；结果按[`cache_key_labeled`]单独缓存。
+pub fn find_combinations_labeled(
+    items: &[(String, f64)],
+    target: f64,
+    tolerance: f64,
+    progress_tx: Option<crossbeam_channel::Sender<f64>>,
+    max_length: usize,
+    max_results: usize,
+    stop_flag: Arc<AtomicBool>,
+) -> Vec<Vec<(String, f64)>> {
+    // 按数值排序以复用meet-in-the-middle的二分查找，但同时记录每个元素在
+    // `items`中的原始下标；`find_combinations_mitm_indices`内部按数值返回
+    // 组合(详见其文档)，这里用原始下标把顺序还原成输入(CSV行)顺序，
+    // 而不是让对账结果意外按金额大小排列。
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|&a, &b| items[a].1.partial_cmp(&items[b].1).unwrap());
+    let sorted_items: Vec<(String, f64)> = order.iter().map(|&i| items[i].clone()).collect();
+
+    let key = cache_key_labeled(&sorted_items, target, tolerance, max_length, max_results);
+    if let Some(cached) = LABELED_CACHE.get(key) {
+        println!("从缓存中找到结果(带标签)");
+        return cached;
+    }
+
+    let sorted_numbers: Vec<f64> = sorted_items.iter().map(|(_, v)| *v).collect();
+
+    let index_results = find_combinations_mitm_indices(
+        &sorted_numbers, target, tolerance, max_length, max_results, &stop_flag, progress_tx.as_ref(),
+    );
+
+    let final_results: Vec<Vec<(String, f64)>> = index_results
+        .into_iter()
+        .map(|mut indices| {
+            indices.sort_by_key(|&i| order[i]);
+            indices.iter().map(|&i| sorted_items[i].clone()).collect()
+        })
+        .collect();
+
+    LABELED_CACHE.put(key, final_results.clone());
+    final_results
+}
+
+/// meet-in-the-middle单侧元素个数达到该值即放弃(每侧最多允许`MAX_MITM_HALF_LEN - 1`个)，卡的是`enumerate_subsets`要
+/// 枚举`2^len`个子集的计算量，而不是`1u64 << len`在`len=64`时的位移溢出。
+const MAX_MITM_HALF_LEN: usize = 27;
+
+/// 枚举一组数字(带全局下标偏移)中所有不超过`max_length`个元素的子集
+///
+/// 返回 `(子集和, 子集在原始数组中的下标列表)`，下标已加上`offset`以便与另一半对齐。
+fn enumerate_subsets(
+    nums: &[f64],
+    offset: usize,
+    max_length: usize,
+    stop_flag: &AtomicBool,
+) -> Vec<(f64, Vec<usize>)> {
+    let len = nums.len();
+    let mut subsets = Vec::with_capacity(1usize << len.min(20));
+
+    for mask in 0u64..(1u64 << len) {
+        if mask % 4096 == 0 && stop_flag.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let count = mask.count_ones() as usize;
+        if count > max_length {
+            continue;
+        }
+
+        let mut sum = 0.0;
+        let mut indices = Vec::with_capacity(count);
+        for (bit, &num) in nums.iter().enumerate() {
+            if mask & (1 << bit) != 0 {
+                sum += num;
+                indices.push(offset + bit);
+            }
+        }
+        subsets.push((sum, indices));
+    }
+
+    subsets
+}
+
+/// meet-in-the-middle求解:将`sorted_numbers`拆成两半L、R，分别枚举子集和，
+/// 再在另一半的有序和列表中二分查找满足`target ± tolerance`的组合。
+/// 复杂度约为`O(2^(n/2) * n)`，可取代指数级回溯处理40~50个元素的输入。
+fn find_combinations_mitm(
+    sorted_numbers: &[f64],
+    target: f64,
+    tolerance: f64,
+    max_length: usize,
+    max_results: usize,
+    stop_flag: &AtomicBool,
+    progress_tx: Option<&Sender<f64>>,
+) -> Vec<Vec<f64>> {
+    find_combinations_mitm_indices(
+        sorted_numbers, target, tolerance, max_length, max_results, stop_flag, progress_tx,
+    )
+    .into_iter()
+    .map(|indices| indices.iter().map(|&i| sorted_numbers[i]).collect())
+    .collect()
+}
+
+/// 同`find_combinations_mitm`，但返回下标而非数值，供需要追溯回原始记录
+/// (如带标签的CSV行)的调用方使用；下标按数值大小排列，原始顺序由调用方自行还原。
+fn find_combinations_mitm_indices(
+    sorted_numbers: &[f64],
+    target: f64,
+    tolerance: f64,
+    max_length: usize,
+    max_results: usize,
+    stop_flag: &AtomicBool,
+    progress_tx: Option<&Sender<f64>>,
+) -> Vec<Vec<usize>> {
+    let mut results = Vec::new();
+    if sorted_numbers.is_empty() {
+        return results;
+    }
+
+    let mid = sorted_numbers.len() / 2;
+    let (left, right) = sorted_numbers.split_at(mid);
+
+    if left.len() >= MAX_MITM_HALF_LEN || right.len() >= MAX_MITM_HALF_LEN {
+        eprintln!(
+            "meet-in-the-middle放弃: 单侧{}个元素的子集枚举量过大(须少于{}个)，请改用DP或回溯引擎",
+            left.len().max(right.len()),
+            MAX_MITM_HALF_LEN
+        );
+        return results;
+    }
+
+    let left_subsets = enumerate_subsets(left, 0, max_length, stop_flag);
+    let mut right_subsets = enumerate_subsets(right, mid, max_length, stop_flag);
+    right_subsets.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let right_sums: Vec<f64> = right_subsets.iter().map(|(sum, _)| *sum).collect();
 
-    // 计算剩余数字的最大可能和
-    fn max_remaining_sum(nums: &[f64], start: usize) -> f64 {
-        nums[start..].iter().sum()
+    println!(
+        "meet-in-the-middle: 左半{}个元素({}个子集), 右半{}个元素({}个子集)",
+        left.len(),
+        left_subsets.len(),
+        right.len(),
+        right_subsets.len()
+    );
+
+    let total_left = left_subsets.len().max(1);
+    for (processed, (left_sum, left_idx)) in left_subsets.iter().enumerate() {
+        if stop_flag.load(Ordering::Relaxed) || results.len() >= max_results {
+            break;
+        }
+
+        // 区间端点由减法求得，浮点舍入可能让区间比"总和落在target ± tolerance"
+        // 略窄(如25.0 - 5.01 != 19.99)，因此先稍微放宽，再按实际总和校验
+        let margin = 4.0 * f64::EPSILON * (target.abs() + left_sum.abs() + tolerance.abs());
+        let low = target - left_sum - tolerance - margin;
+        let high = target - left_sum + tolerance + margin;
+        let start = right_sums.partition_point(|&s| s < low);
+
+        for (right_sum, right_idx) in &right_subsets[start..] {
+            if *right_sum > high {
+                break;
+            }
+            if ((left_sum + right_sum) - target).abs() > tolerance {
+                continue;
+            }
+            if left_idx.is_empty() && right_idx.is_empty() {
+                continue;
+            }
+            if left_idx.len() + right_idx.len() > max_length {
+                continue;
+            }
+
+            let mut combined: Vec<usize> = left_idx.iter().chain(right_idx.iter()).copied().collect();
+            combined.sort_unstable();
+
+            println!(
+                "找到解: {:?} (总和: {:.2}, 目标: {:.2})",
+                combined.iter().map(|&i| sorted_numbers[i]).collect::<Vec<f64>>(),
+                left_sum + right_sum,
+                target
+            );
+            results.push(combined);
+            if results.len() >= max_results {
+                break;
+            }
+        }
+
+        if let Some(tx) = progress_tx {
+            let _ = tx.send((processed + 1) as f64 / total_left as f64);
+        }
     }
 
+    results
+}
+
+/// 回溯求解(带剪枝和并行计算)，在元素较少时与meet-in-the-middle结果一致，
+/// 但子集空间随输入规模呈`2^n`指数增长，超过约30个元素会明显变慢。
+pub fn find_combinations_backtrack(
+    numbers: &[f64],
+    target: f64,
+    tolerance: f64,
+    max_length: usize,
+    max_results: usize,
+    stop_flag: Arc<AtomicBool>,
+) -> Vec<Vec<f64>> {
+    let mut sorted_numbers = numbers.to_vec();
+    sorted_numbers.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
     // 优化版回溯函数(带剪枝和并行计算)
     fn optimized_backtrack(
         nums: &[f64],
@@ -87,7 +367,7 @@ pub fn find_combinations(
         // 检查当前路径是否满足条件
         let sum = path.iter().sum::<f64>();
         let diff = (sum - target).abs();
-        
+
         if diff <= tolerance && !path.is_empty() {
             println!("找到解: {:?} (总和: {:.2}, 目标: {:.2}, 误差: {:.2})", path, sum, target, diff);
             if results.lock().unwrap().len() < max_results {
@@ -97,8 +377,9 @@ pub fn find_combinations(
         }
 
         // 放宽剪枝条件: 仅保留结果数量限制和停止标志检查
-        if results.lock().unwrap().len() >= max_results || 
-           stop_flag.load(Ordering::Relaxed) {
+        if results.lock().unwrap().len() >= max_results ||
+           stop_flag.load(Ordering::Relaxed) ||
+           path.len() >= max_length {
             return;
         }
 
@@ -107,11 +388,11 @@ pub fn find_combinations(
             if stop_flag.load(Ordering::Relaxed) {
                 return;
             }
-            
+
             let mut local_path = path.clone();
             local_path.push(nums[i]);
             optimized_backtrack(
-                nums, target, tolerance, i + 1, 
+                nums, target, tolerance, i + 1,
                 &mut local_path, results.clone(), max_results, max_length, stop_flag
             );
         });
@@ -122,30 +403,312 @@ pub fn find_combinations(
         &sorted_numbers, target, tolerance, 0,
         &mut Vec::new(), results.clone(), max_results, max_length, &stop_flag
     );
-    
-    let local_results = results.lock().unwrap().clone();
-    
-    let final_results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
-    
-    // 存入缓存
-    CACHE.put(target, tolerance, final_results.clone());
-    final_results
+
+    Arc::try_unwrap(results).unwrap().into_inner().unwrap()
+}
+
+/// 允许重复使用输入值的回溯求解:`max_uses[i]`限定下标`i`对应的数值在
+/// 一个组合中最多可出现的次数(`1`等价于不重复，`usize::MAX`则不限)。
+pub fn find_combinations_with_repetition(
+    numbers: &[f64],
+    max_uses: &[usize],
+    target: f64,
+    tolerance: f64,
+    max_length: usize,
+    max_results: usize,
+    stop_flag: Arc<AtomicBool>,
+) -> Vec<Vec<f64>> {
+    assert_eq!(numbers.len(), max_uses.len(), "numbers和max_uses长度必须一致");
+
+    // 带重复次数上限的回溯函数(带剪枝和并行计算)
+    #[allow(clippy::too_many_arguments)] // 与上面的optimized_backtrack同理，递归状态拆成独立字段而非打包成结构体
+    fn backtrack_with_repetition(
+        numbers: &[f64],
+        max_uses: &[usize],
+        target: f64,
+        tolerance: f64,
+        idx: usize,
+        path: &mut Vec<f64>,
+        results: Arc<Mutex<Vec<Vec<f64>>>>,
+        max_results: usize,
+        max_length: usize,
+        stop_flag: &AtomicBool,
+    ) {
+        // 检查停止标志
+        if stop_flag.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // 检查当前路径是否满足条件
+        let sum = path.iter().sum::<f64>();
+        let diff = (sum - target).abs();
+
+        if diff <= tolerance && !path.is_empty() {
+            println!("找到解(允许重复): {:?} (总和: {:.2}, 目标: {:.2}, 误差: {:.2})", path, sum, target, diff);
+            if results.lock().unwrap().len() < max_results {
+                results.lock().unwrap().push(path.clone());
+            }
+            return;
+        }
+
+        // 放宽剪枝条件: 仅保留结果数量限制、停止标志、最大长度和下标越界检查
+        if results.lock().unwrap().len() >= max_results ||
+           stop_flag.load(Ordering::Relaxed) ||
+           path.len() >= max_length ||
+           idx >= numbers.len() {
+            return;
+        }
+
+        // 当前下标最多可使用的次数: 受限于`max_uses[idx]`和剩余的`max_length`空间
+        let cap = max_uses[idx].min(max_length - path.len());
+
+        // 并行处理"使用0次到cap次当前下标，再前进到下一下标"的各个分支
+        (0..=cap).into_par_iter().for_each(|count| {
+            if stop_flag.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let mut local_path = path.clone();
+            for _ in 0..count {
+                local_path.push(numbers[idx]);
+            }
+            backtrack_with_repetition(
+                numbers, max_uses, target, tolerance, idx + 1,
+                &mut local_path, results.clone(), max_results, max_length, stop_flag
+            );
+        });
+    }
+
+    let results = Arc::new(Mutex::new(Vec::new()));
+    backtrack_with_repetition(
+        numbers, max_uses, target, tolerance, 0,
+        &mut Vec::new(), results.clone(), max_results, max_length, &stop_flag
+    );
+
+    Arc::try_unwrap(results).unwrap().into_inner().unwrap()
+}
+
+/// 将金额放大100倍并四舍五入为整数分，用于精确的整数DP
+fn scale_to_cents(value: f64) -> i64 {
+    (value * 100.0).round() as i64
+}
+
+/// DP可达性表的单元格数量上限(`(cap+1) * (元素个数+1)`，约200MB的`Vec<bool>`)，
+/// 超过则直接放弃该模式而不是瞬间分配数GB内存
+const MAX_DP_TABLE_CELLS: usize = 200_000_000;
+
+/// 精确整数DP求解:按`scale_to_cents`把金额和目标放大为整数分，构建可达性表
+/// `reach[i][s]`后回溯重建组合，不受浮点误差影响，适合对账等货币场景。
+/// 只支持非负金额，返回值第二项是被跳过的负数行数。
+pub fn find_combinations_dp(
+    numbers: &[f64],
+    target: f64,
+    tolerance: f64,
+    max_length: usize,
+    max_results: usize,
+    stop_flag: Arc<AtomicBool>,
+) -> (Vec<Vec<f64>>, usize) {
+    let (items, cap, skipped) = match build_dp_items(numbers, target, tolerance) {
+        Some(v) => v,
+        None => return (Vec::new(), 0),
+    };
+
+    let reach = build_reach_table(&items, cap, &stop_flag);
+    let n = items.len();
+
+    let scaled_target = scale_to_cents(target);
+    let scaled_tolerance = scale_to_cents(tolerance).abs();
+    let low = (scaled_target - scaled_tolerance).max(0) as usize;
+
+    let mut results = Vec::new();
+    for s in low..=cap {
+        if stop_flag.load(Ordering::Relaxed) || results.len() >= max_results {
+            break;
+        }
+        if reach[n][s] {
+            reconstruct_dp(
+                &reach, &items, numbers, n, s, &mut Vec::new(),
+                max_length, max_results, &stop_flag, &mut results,
+            );
+        }
+    }
+
+    (results, skipped)
+}
+
+/// 只统计落在`target ± tolerance`区间内、长度不超过`max_length`的子集数量，
+/// 不重建具体组合，用于在结果过多、无需全部展示时快速了解解的多重性。
+/// 返回值的第二项同[`find_combinations_dp`]，是被跳过的负数行数。
+pub fn count_combinations_dp(
+    numbers: &[f64],
+    target: f64,
+    tolerance: f64,
+    max_length: usize,
+    stop_flag: Arc<AtomicBool>,
+) -> (u64, usize) {
+    let (items, cap, skipped) = match build_dp_items(numbers, target, tolerance) {
+        Some(v) => v,
+        None => return (0, 0),
+    };
+
+    // count[k][s]: 恰好用k个item凑出和s的子集数，k不超过`max_length`与item
+    // 数量中较小者，与`reconstruct_dp`对`max_length`的限制保持一致。
+    let cap_len = max_length.min(items.len());
+
+    // 每个单元格是u64(8字节)而非`reach`的bool(1字节)，因此复用
+    // `MAX_DP_TABLE_CELLS`前先按字节大小换算，避免`cap_len`接近item数量时
+    // 悄悄超出该上限原本约200MB的预算。
+    let count_table_cells = (cap + 1).saturating_mul(cap_len + 1);
+    if count_table_cells > MAX_DP_TABLE_CELLS / std::mem::size_of::<u64>() {
+        eprintln!(
+            "DP模式放弃(--count-only): 计数表需要约{}个u64单元格，超过上限{}个，请改用meet-in-the-middle或回溯引擎",
+            count_table_cells,
+            MAX_DP_TABLE_CELLS / std::mem::size_of::<u64>()
+        );
+        return (0, skipped);
+    }
+
+    let mut count = vec![vec![0u64; cap + 1]; cap_len + 1];
+    count[0][0] = 1;
+    for &(_, v) in &items {
+        if stop_flag.load(Ordering::Relaxed) {
+            break;
+        }
+        let v = v as usize;
+        for k in (1..=cap_len).rev() {
+            for s in (v..=cap).rev() {
+                count[k][s] = count[k][s].saturating_add(count[k - 1][s - v]);
+            }
+        }
+    }
+
+    let scaled_target = scale_to_cents(target);
+    let scaled_tolerance = scale_to_cents(tolerance).abs();
+    let low = (scaled_target - scaled_tolerance).max(0) as usize;
+
+    let total = count[1..=cap_len].iter().fold(0u64, |acc, row| {
+        acc.saturating_add(row[low..=cap].iter().fold(0u64, |a, &c| a.saturating_add(c)))
+    });
+    (total, skipped)
+}
+
+/// 将输入金额放大为整数分，过滤负数(DP引擎暂不支持)，返回上界`cap`
+/// (`scaled_target + scaled_tolerance`)和被跳过的负数行数；上界为负或
+/// 可达性表会超过[`MAX_DP_TABLE_CELLS`]时放弃，返回`None`。
+fn build_dp_items(numbers: &[f64], target: f64, tolerance: f64) -> Option<(Vec<(usize, i64)>, usize, usize)> {
+    let scaled_target = scale_to_cents(target);
+    let scaled_tolerance = scale_to_cents(tolerance).abs();
+    let cap = scaled_target + scaled_tolerance;
+    if cap < 0 {
+        return None;
+    }
+    let cap = cap as usize;
+
+    let mut skipped = 0usize;
+    let items: Vec<(usize, i64)> = numbers
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| (i, scale_to_cents(x)))
+        .filter(|&(i, cents)| {
+            if cents < 0 {
+                eprintln!("DP模式跳过负数输入(下标{}): {:.2}", i, numbers[i]);
+                skipped += 1;
+                false
+            } else {
+                true
+            }
+        })
+        .collect();
+
+    let table_cells = (cap + 1).saturating_mul(items.len() + 1);
+    if table_cells > MAX_DP_TABLE_CELLS {
+        eprintln!(
+            "DP模式放弃: 可达性表需要约{}个单元格，超过上限{}个，请改用meet-in-the-middle或回溯引擎",
+            table_cells, MAX_DP_TABLE_CELLS
+        );
+        return None;
+    }
+
+    Some((items, cap, skipped))
+}
+
+/// 构建可达性表: `reach[i][s]` 表示前`i`个item的子集中是否存在和为`s`的组合
+fn build_reach_table(items: &[(usize, i64)], cap: usize, stop_flag: &AtomicBool) -> Vec<Vec<bool>> {
+    let n = items.len();
+    let mut reach = vec![vec![false; cap + 1]; n + 1];
+    reach[0][0] = true;
+
+    for i in 1..=n {
+        if stop_flag.load(Ordering::Relaxed) {
+            break;
+        }
+        let v = items[i - 1].1 as usize;
+        for s in 0..=cap {
+            reach[i][s] = reach[i - 1][s] || (s >= v && reach[i - 1][s - v]);
+        }
+    }
+
+    reach
+}
+
+/// 从可达性表`(i, s)`向后回溯，枚举所有和为`s`的子集，受`max_length`和
+/// `max_results`约束; `path`中存放的是`numbers`里的原始下标。
+#[allow(clippy::too_many_arguments)] // 与optimized_backtrack同理，递归状态拆成独立字段而非打包成结构体
+fn reconstruct_dp(
+    reach: &[Vec<bool>],
+    items: &[(usize, i64)],
+    numbers: &[f64],
+    i: usize,
+    s: usize,
+    path: &mut Vec<usize>,
+    max_length: usize,
+    max_results: usize,
+    stop_flag: &AtomicBool,
+    results: &mut Vec<Vec<f64>>,
+) {
+    if stop_flag.load(Ordering::Relaxed) || results.len() >= max_results {
+        return;
+    }
+
+    if i == 0 {
+        if s == 0 && !path.is_empty() {
+            let mut indices = path.clone();
+            indices.sort_unstable();
+            results.push(indices.iter().map(|&idx| numbers[idx]).collect());
+        }
+        return;
+    }
+
+    let (orig_idx, v) = items[i - 1];
+    let v = v as usize;
+
+    // 不选当前item
+    if reach[i - 1][s] {
+        reconstruct_dp(reach, items, numbers, i - 1, s, path, max_length, max_results, stop_flag, results);
+    }
+
+    // 选当前item
+    if s >= v && reach[i - 1][s - v] && path.len() < max_length {
+        path.push(orig_idx);
+        reconstruct_dp(reach, items, numbers, i - 1, s - v, path, max_length, max_results, stop_flag, results);
+        path.pop();
+    }
 }
 
 /// 从CSV文件读取数字(支持单列和多列格式)
 pub fn read_numbers_from_csv(path: &str) -> Result<Vec<f64>, Box<dyn Error>> {
     let content = std::fs::read_to_string(path)?;
-    
+
     // 先尝试按行解析(单列CSV)
     let line_numbers: Vec<f64> = content
         .lines()
         .filter_map(|line| line.trim().parse::<f64>().ok())
         .collect();
-    
+
     if !line_numbers.is_empty() {
         return Ok(line_numbers);
     }
-    
+
     // 如果按行解析没有数据，尝试标准CSV解析(多列)
     let mut rdr = csv::Reader::from_path(path)?;
     let mut numbers = Vec::new();
@@ -157,10 +720,46 @@ pub fn read_numbers_from_csv(path: &str) -> Result<Vec<f64>, Box<dyn Error>> {
             }
         }
     }
-    
+
     Ok(numbers)
 }
 
+/// 从CSV文件读取带标签的数字:支持两列格式(取最后一列为数值、其余列拼接为
+/// 标签)和单列格式(合成`row_N`标签)。按列解析，带逗号的引号标签也能正确还原。
+pub fn read_labeled_numbers_from_csv(path: &str) -> Result<Vec<(String, f64)>, Box<dyn Error>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+
+    let mut items = Vec::new();
+    for (i, result) in rdr.records().enumerate() {
+        let record = result?;
+        if let Some(item) = parse_labeled_fields(&record, i) {
+            items.push(item);
+        }
+    }
+
+    Ok(items)
+}
+
+/// 解析已按列拆分好的一行，`row_index`在缺少标签时用于生成`row_N`
+fn parse_labeled_fields(record: &csv::StringRecord, row_index: usize) -> Option<(String, f64)> {
+    if record.is_empty() {
+        return None;
+    }
+
+    let fields: Vec<&str> = record.iter().map(|f| f.trim()).collect();
+    if fields.len() >= 2 {
+        if let Ok(value) = fields[fields.len() - 1].parse::<f64>() {
+            let label = fields[..fields.len() - 1].join(",");
+            return Some((label, value));
+        }
+    }
+
+    fields[0].parse::<f64>().ok().map(|value| (format!("row_{}", row_index), value))
+}
+
 /// 从TXT文件读取数字(每行一个数字)
 pub fn read_numbers_from_txt(path: &str) -> Result<Vec<f64>, Box<dyn Error>> {
     let content = std::fs::read_to_string(path)?;
@@ -168,7 +767,7 @@ pub fn read_numbers_from_txt(path: &str) -> Result<Vec<f64>, Box<dyn Error>> {
         .lines()
         .filter_map(|line| line.trim().parse::<f64>().ok())
         .collect();
-    
+
     Ok(numbers)
 }
 
@@ -184,28 +783,267 @@ mod tests {
         let target = 5.0;
         let tolerance = 0.1;
         let stop_flag = Arc::new(AtomicBool::new(false));
-        
+
         // 测试精确匹配
-        let result = find_combinations(&numbers, target, tolerance, None, 5, stop_flag.clone());
+        let result = find_combinations(&numbers, target, tolerance, None, 5, 1000, stop_flag.clone());
         assert!(result.iter().any(|r| (r.iter().sum::<f64>() - target).abs() <= tolerance));
-        
-        // 测试进度报告
+
+        // 测试进度报告(max_length与上面不同，确保是一次新实例而非命中缓存，
+        // 否则不会真正调用`find_combinations_mitm_indices`、进度也就不会发送)
         let (sender, receiver) = unbounded();
-        find_combinations(&numbers, target, tolerance, Some(sender), 5, stop_flag.clone());
+        find_combinations(&numbers, target, tolerance, Some(sender), 4, 1000, stop_flag.clone());
         assert!(receiver.try_recv().is_ok());
-        
+
         // 测试边界情况
-        let empty_result = find_combinations(&[], target, tolerance, None, 5, stop_flag);
+        let empty_result = find_combinations(&[], target, tolerance, None, 5, 1000, stop_flag);
         assert!(empty_result.is_empty());
     }
 
+    #[test]
+    fn test_find_combinations_mitm_matches_backtrack() {
+        let numbers = vec![1.5, 2.5, 3.0, 4.0, 6.5, 8.0];
+        let target = 10.5;
+        let tolerance = 0.01;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let mitm_result = find_combinations(&numbers, target, tolerance, None, 6, 1000, stop_flag.clone());
+        let backtrack_result = find_combinations_backtrack(&numbers, target, tolerance, 6, 1000, stop_flag);
+
+        let mut mitm_sums: Vec<i64> = mitm_result.iter().map(|r| (r.iter().sum::<f64>() * 100.0).round() as i64).collect();
+        let mut backtrack_sums: Vec<i64> = backtrack_result.iter().map(|r| (r.iter().sum::<f64>() * 100.0).round() as i64).collect();
+        mitm_sums.sort();
+        backtrack_sums.sort();
+        assert_eq!(mitm_sums, backtrack_sums);
+    }
+
+    #[test]
+    fn test_find_combinations_mitm_guards_half_length_overflow() {
+        // 130个元素时mid=65，两侧都会达到`MAX_MITM_HALF_LEN`，必须提前放弃
+        // 而不是让`1u64 << len`溢出(release下静默漏解，debug下panic)。
+        let numbers: Vec<f64> = (0..130).map(|i| i as f64 + 1.0).collect();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let result = find_combinations(&numbers, 1.0, 0.0, None, 5, 10, stop_flag);
+        assert!(result.is_empty());
+    }
+
     #[test]
     fn test_read_numbers_from_csv() {
         let temp_file = std::env::temp_dir().join("test_numbers.csv");
         std::fs::write(&temp_file, "1.0\n2.0\n3.0").unwrap();
-        
+
         let result = read_numbers_from_csv(temp_file.to_str().unwrap());
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), vec![1.0, 2.0, 3.0]);
     }
+
+    #[test]
+    fn test_read_labeled_numbers_from_csv_two_column() {
+        let temp_file = std::env::temp_dir().join("test_labeled_numbers.csv");
+        std::fs::write(&temp_file, "invoice_a,19.99\ninvoice_b,5.01\ninvoice_c,10.00").unwrap();
+
+        let result = read_labeled_numbers_from_csv(temp_file.to_str().unwrap()).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                ("invoice_a".to_string(), 19.99),
+                ("invoice_b".to_string(), 5.01),
+                ("invoice_c".to_string(), 10.00),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_labeled_numbers_from_csv_quoted_label_with_comma() {
+        let temp_file = std::env::temp_dir().join("test_labeled_numbers_quoted.csv");
+        std::fs::write(&temp_file, "\"Smith, John\",19.99\n\"Doe, Jane\",5.01").unwrap();
+
+        let result = read_labeled_numbers_from_csv(temp_file.to_str().unwrap()).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                ("Smith, John".to_string(), 19.99),
+                ("Doe, Jane".to_string(), 5.01),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_labeled_numbers_from_csv_single_column_synthesizes_labels() {
+        let temp_file = std::env::temp_dir().join("test_labeled_numbers_single.csv");
+        std::fs::write(&temp_file, "1.0\n2.0\n3.0").unwrap();
+
+        let result = read_labeled_numbers_from_csv(temp_file.to_str().unwrap()).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                ("row_0".to_string(), 1.0),
+                ("row_1".to_string(), 2.0),
+                ("row_2".to_string(), 3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_combinations_labeled_tracks_matching_rows() {
+        let items = vec![
+            ("invoice_a".to_string(), 19.99),
+            ("invoice_b".to_string(), 5.01),
+            ("invoice_c".to_string(), 10.00),
+        ];
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let result = find_combinations_labeled(&items, 25.00, 0.0, None, 3, 1000, stop_flag);
+        assert!(result.iter().any(|r| {
+            let labels: Vec<&str> = r.iter().map(|(label, _)| label.as_str()).collect();
+            labels == ["invoice_a", "invoice_b"]
+        }));
+    }
+
+    #[test]
+    fn test_find_combinations_dp() {
+        let numbers = vec![19.99, 5.01, 10.00, 25.00];
+        let target = 25.00;
+        let tolerance = 0.0;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let (result, skipped) = find_combinations_dp(&numbers, target, tolerance, 4, 1000, stop_flag);
+        assert!(result.iter().any(|r| (r.iter().sum::<f64>() - target).abs() < 0.001));
+        assert!(result.iter().any(|r| r.len() == 1 && (r[0] - 25.00).abs() < 0.001));
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn test_count_combinations_dp() {
+        let numbers = vec![19.99, 5.01, 10.00, 25.00];
+        let target = 25.00;
+        let tolerance = 0.0;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let (count, skipped) = count_combinations_dp(&numbers, target, tolerance, 4, stop_flag.clone());
+        let (materialized, _) = find_combinations_dp(&numbers, target, tolerance, 4, 1000, stop_flag);
+        assert_eq!(count as usize, materialized.len());
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn test_count_combinations_dp_respects_max_length() {
+        // 19.99+5.01 = 25.00 需要2个item；把max_length限制到1时，
+        // 只有单独的25.00这一行能命中，计数必须和同样max_length下
+        // find_combinations_dp实际重建出的解数一致。
+        let numbers = vec![19.99, 5.01, 10.00, 25.00];
+        let target = 25.00;
+        let tolerance = 0.0;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let (count, _) = count_combinations_dp(&numbers, target, tolerance, 1, stop_flag.clone());
+        let (materialized, _) = find_combinations_dp(&numbers, target, tolerance, 1, 1000, stop_flag);
+        assert_eq!(count as usize, materialized.len());
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_count_combinations_dp_guards_byte_heavier_table() {
+        // 500个item、目标1000.00时cap_len与cap使count的u64计数表约5000万格:
+        // 低于reach表(bool，上限2亿格)的阈值，但高于count表(u64，上限2500万格)
+        // 按字节换算后的阈值，必须单独放弃而不是复用reach表的判断标准。
+        let numbers: Vec<f64> = (0..500).map(|i| i as f64 + 1.0).collect();
+        let target = 1000.00;
+        let tolerance = 0.0;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let (count, _) = count_combinations_dp(&numbers, target, tolerance, 500, stop_flag);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_find_combinations_dp_reports_skipped_negative_rows() {
+        let numbers = vec![10.00, -5.00, 15.00];
+        let target = 15.00;
+        let tolerance = 0.0;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let (result, skipped) = find_combinations_dp(&numbers, target, tolerance, 4, 1000, stop_flag);
+        assert_eq!(skipped, 1);
+        assert!(result.iter().any(|r| r.len() == 1 && (r[0] - 15.00).abs() < 0.001));
+    }
+
+    #[test]
+    fn test_find_combinations_with_repetition_unlimited() {
+        let numbers = vec![2.0, 5.0];
+        let max_uses = vec![usize::MAX, usize::MAX];
+        let target = 12.0;
+        let tolerance = 0.0;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let result = find_combinations_with_repetition(
+            &numbers, &max_uses, target, tolerance, 6, 1000, stop_flag,
+        );
+        // 12 = 2*6 或 2*1 + 5*2
+        assert!(result.iter().any(|r| r.len() == 6 && r.iter().all(|&v| v == 2.0)));
+        assert!(result.iter().any(|r| {
+            let twos = r.iter().filter(|&&v| v == 2.0).count();
+            let fives = r.iter().filter(|&&v| v == 5.0).count();
+            twos == 1 && fives == 2
+        }));
+    }
+
+    #[test]
+    fn test_find_combinations_with_repetition_respects_per_item_cap() {
+        let numbers = vec![5.0];
+        let max_uses = vec![3];
+        let target = 20.0; // 需要用到5.0四次才能凑够，但上限只有3次
+        let tolerance = 0.0;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let result = find_combinations_with_repetition(
+            &numbers, &max_uses, target, tolerance, 10, 1000, stop_flag,
+        );
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_cache_key_differs_on_numbers_and_max_length() {
+        let base = cache_key(&[1.0, 2.0, 3.0], 5.0, 0.1, 3, 1000, "mitm");
+        let different_numbers = cache_key(&[1.0, 2.0, 4.0], 5.0, 0.1, 3, 1000, "mitm");
+        let different_max_length = cache_key(&[1.0, 2.0, 3.0], 5.0, 0.1, 4, 1000, "mitm");
+        assert_ne!(base, different_numbers);
+        assert_ne!(base, different_max_length);
+    }
+
+    #[test]
+    fn test_cache_key_matches_on_identical_instance() {
+        let a = cache_key(&[1.0, 2.0, 3.0], 5.0, 0.1, 3, 1000, "mitm");
+        let b = cache_key(&[1.0, 2.0, 3.0], 5.0, 0.1, 3, 1000, "mitm");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_find_combinations_cache_does_not_leak_across_instances() {
+        // 修复前: 缓存只按target/tolerance键入，下面两次调用会被误判为同一问题，
+        // 第二次查询会错误地拿到第一次(数字集合不同)的结果。
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let result_a = find_combinations(&[1.0, 4.0], 5.0, 0.0, None, 2, 1000, stop_flag.clone());
+        let result_b = find_combinations(&[2.0, 10.0], 5.0, 0.0, None, 2, 1000, stop_flag);
+
+        assert!(result_a.iter().any(|r| r.iter().sum::<f64>() == 5.0));
+        assert!(result_b.is_empty());
+    }
+
+    #[test]
+    fn test_find_combinations_labeled_cache_keys_on_labels_too() {
+        // 两组输入数值完全相同，但标签不同；若缓存只按数值键入，第二次调用
+        // 会错误地复用第一次的标签。
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let items_a = vec![("invoice_x".to_string(), 2.0), ("invoice_y".to_string(), 3.0)];
+        let items_b = vec![("po_1".to_string(), 2.0), ("po_2".to_string(), 3.0)];
+
+        let result_a = find_combinations_labeled(&items_a, 5.0, 0.0, None, 2, 1000, stop_flag.clone());
+        let result_b = find_combinations_labeled(&items_b, 5.0, 0.0, None, 2, 1000, stop_flag);
+
+        let labels_a: Vec<&str> = result_a[0].iter().map(|(label, _)| label.as_str()).collect();
+        let labels_b: Vec<&str> = result_b[0].iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels_a, ["invoice_x", "invoice_y"]);
+        assert_eq!(labels_b, ["po_1", "po_2"]);
+    }
 }
@@ -1,27 +1,287 @@
+use clap::{Parser, Subcommand, ValueEnum};
 use eframe::egui;
-use sum3_solver::{find_combinations, read_numbers_from_csv, read_numbers_from_txt};
+use sum3_solver::{
+    configure_cache, count_combinations_dp, find_combinations_backtrack, find_combinations_dp,
+    find_combinations_labeled, find_combinations_with_repetition, read_labeled_numbers_from_csv,
+    read_numbers_from_txt,
+};
+use std::error::Error;
 use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 
+#[derive(Parser)]
+#[command(name = "sum3", about = "数字组合求解器")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 无界面批处理模式:从文件读取数字，计算组合并输出或写出结果
+    Batch(BatchArgs),
+}
+
+#[derive(Parser)]
+struct BatchArgs {
+    /// 输入文件(.csv支持"标签,数值"两列格式，.txt为单列)
+    #[arg(long)]
+    input: String,
+    #[arg(long)]
+    target: f64,
+    #[arg(long, default_value_t = 0.01)]
+    tolerance: f64,
+    #[arg(long = "max-length", default_value_t = 5)]
+    max_length: usize,
+    #[arg(long = "max-results", default_value_t = 1000)]
+    max_results: usize,
+    /// 求解引擎
+    #[arg(long, value_enum, default_value_t = Engine::Mitm)]
+    engine: Engine,
+    /// 每个数值最多可重复使用的次数，仅`--engine repetition`时生效。省略则不限，
+    /// 单个数值表示所有项共用同一上限，逗号分隔的多个数值("3,1,5")则按输入顺序
+    /// 为每一项分别设置上限(数量须与输入行数一致)
+    #[arg(long = "max-uses")]
+    max_uses: Option<String>,
+    /// 只统计落在目标范围内的组合数量，不重建具体组合；只能与`--engine dp`同时使用，
+    /// 搭配其他引擎时报错退出
+    #[arg(long = "count-only", default_value_t = false)]
+    count_only: bool,
+    /// 结果缓存容量(条目数)
+    #[arg(long = "cache-capacity", default_value_t = 1_000_000)]
+    cache_capacity: usize,
+    /// 禁用结果缓存，每次调用都重新计算
+    #[arg(long = "no-cache", default_value_t = false)]
+    no_cache: bool,
+    /// 输出文件，省略则打印到标准输出
+    #[arg(long)]
+    output: Option<String>,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Txt)]
+    format: OutputFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Engine {
+    Backtrack,
+    Mitm,
+    Dp,
+    /// 允许重复使用同一数值，每个数值的重复上限见`--max-uses`
+    Repetition,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Csv,
+    Txt,
+}
+
+/// 从文件加载带标签的数字: .csv支持"标签,数值"两列格式，.txt为单列文件，
+/// 标签用行号合成(`row_0`, `row_1`, …)。GUI导入和批处理CLI共用此逻辑。
+fn load_labeled_items(path: &str) -> Result<Vec<(String, f64)>, Box<dyn Error>> {
+    if path.ends_with(".csv") {
+        read_labeled_numbers_from_csv(path)
+    } else {
+        Ok(read_numbers_from_txt(path)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (format!("row_{}", i), v))
+            .collect())
+    }
+}
+
+/// 将不带标签的数值结果套上"数值本身即标签"的壳，方便与`find_combinations_labeled`
+/// 的输出统一展示(回溯/DP引擎目前不追踪原始行，标签就是格式化后的数值)。
+fn label_by_value(results: Vec<Vec<f64>>) -> Vec<Vec<(String, f64)>> {
+    results
+        .into_iter()
+        .map(|combo| combo.into_iter().map(|v| (format!("{:.2}", v), v)).collect())
+        .collect()
+}
+
+/// 解析`--max-uses`/GUI对应输入，供`find_combinations_with_repetition`的
+/// 每项上限使用:留空表示不限，单个数值对所有item应用同一上限，逗号分隔的
+/// 多个数值则按输入顺序一一对应每个item的独立上限(数量必须等于`item_count`)。
+fn parse_max_uses(spec: &str, item_count: usize) -> Result<Vec<usize>, String> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Ok(vec![usize::MAX; item_count]);
+    }
+
+    let parts: Vec<&str> = spec.split(',').map(|p| p.trim()).collect();
+    if parts.len() == 1 {
+        let cap = parts[0].parse::<usize>().map_err(|_| "无效的最大使用次数".to_string())?;
+        return Ok(vec![cap; item_count]);
+    }
+
+    if parts.len() != item_count {
+        return Err(format!(
+            "最大使用次数的数量({})与输入数字的数量({})不一致",
+            parts.len(),
+            item_count
+        ));
+    }
+
+    parts
+        .iter()
+        .map(|p| p.parse::<usize>().map_err(|_| "无效的最大使用次数".to_string()))
+        .collect()
+}
+
+fn format_solution(index: usize, res: &[(String, f64)]) -> String {
+    let sum = res.iter().map(|(_, v)| v).sum::<f64>();
+    let labeled: Vec<String> = res.iter().map(|(label, v)| format!("{}={:.2}", label, v)).collect();
+    format!("解 {}: [{}] (总和: {:.2})", index + 1, labeled.join(", "), sum)
+}
+
+fn write_results(results: &[Vec<(String, f64)>], path: &str, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Json => {
+            let payload: Vec<serde_json::Value> = results
+                .iter()
+                .map(|res| {
+                    let sum = res.iter().map(|(_, v)| v).sum::<f64>();
+                    let items: Vec<serde_json::Value> = res
+                        .iter()
+                        .map(|(label, value)| serde_json::json!({ "label": label, "value": value }))
+                        .collect();
+                    serde_json::json!({ "sum": sum, "items": items })
+                })
+                .collect();
+            std::fs::write(path, serde_json::to_string_pretty(&payload)?)?;
+        }
+        OutputFormat::Csv => {
+            let mut wtr = csv::Writer::from_path(path)?;
+            for res in results {
+                let sum = res.iter().map(|(_, v)| v).sum::<f64>();
+                let mut record = vec![format!("{:.2}", sum)];
+                record.extend(res.iter().map(|(label, v)| format!("{}={:.2}", label, v)));
+                wtr.write_record(&record)?;
+            }
+            wtr.flush()?;
+        }
+        OutputFormat::Txt => {
+            let lines: Vec<String> = results.iter().enumerate().map(|(i, res)| format_solution(i, res)).collect();
+            std::fs::write(path, lines.join("\n"))?;
+        }
+    }
+    Ok(())
+}
+
+/// 批处理模式: 读取输入、求解、输出结果，全程不启动egui。Ctrl-C会置位
+/// `stop_flag`，由各引擎的停止标志检查点尽快中止计算。
+fn run_batch(args: BatchArgs) {
+    configure_cache(args.cache_capacity, !args.no_cache);
+
+    let items = match load_labeled_items(&args.input) {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("读取输入文件失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("从 {} 读取到 {} 个数字", args.input, items.len());
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    {
+        let stop_flag = stop_flag.clone();
+        if let Err(e) = ctrlc::set_handler(move || {
+            println!("收到中断信号，正在停止计算...");
+            stop_flag.store(true, Ordering::Relaxed);
+        }) {
+            eprintln!("无法注册Ctrl-C处理器: {}", e);
+        }
+    }
+
+    if args.count_only {
+        if !matches!(args.engine, Engine::Dp) {
+            eprintln!("--count-only 仅支持 --engine dp");
+            std::process::exit(1);
+        }
+        let values: Vec<f64> = items.iter().map(|(_, v)| *v).collect();
+        let (count, skipped) =
+            count_combinations_dp(&values, args.target, args.tolerance, args.max_length, stop_flag);
+        if skipped > 0 {
+            eprintln!("DP引擎跳过了 {} 行负数输入，计数可能不完整", skipped);
+        }
+        println!("落在目标范围内的组合数: {}", count);
+        return;
+    }
+
+    let results = match args.engine {
+        Engine::Mitm => find_combinations_labeled(
+            &items, args.target, args.tolerance, None, args.max_length, args.max_results, stop_flag,
+        ),
+        Engine::Backtrack => {
+            let values: Vec<f64> = items.iter().map(|(_, v)| *v).collect();
+            label_by_value(find_combinations_backtrack(
+                &values, args.target, args.tolerance, args.max_length, args.max_results, stop_flag,
+            ))
+        }
+        Engine::Dp => {
+            let values: Vec<f64> = items.iter().map(|(_, v)| *v).collect();
+            let (solutions, skipped) = find_combinations_dp(
+                &values, args.target, args.tolerance, args.max_length, args.max_results, stop_flag,
+            );
+            if skipped > 0 {
+                eprintln!("DP引擎跳过了 {} 行负数输入，结果可能不完整", skipped);
+            }
+            label_by_value(solutions)
+        }
+        Engine::Repetition => {
+            let values: Vec<f64> = items.iter().map(|(_, v)| *v).collect();
+            let max_uses = match parse_max_uses(args.max_uses.as_deref().unwrap_or(""), values.len()) {
+                Ok(m) => m,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            label_by_value(find_combinations_with_repetition(
+                &values, &max_uses, args.target, args.tolerance, args.max_length, args.max_results, stop_flag,
+            ))
+        }
+    };
+    println!("找到 {} 个解", results.len());
+
+    match &args.output {
+        Some(path) => {
+            if let Err(e) = write_results(&results, path, args.format) {
+                eprintln!("写出结果失败: {}", e);
+                std::process::exit(1);
+            }
+            println!("结果已写入 {}", path);
+        }
+        None => {
+            for (i, res) in results.iter().enumerate() {
+                println!("{}", format_solution(i, res));
+            }
+        }
+    }
+}
+
 struct Sum3App {
-    numbers: Vec<f64>,
+    items: Vec<(String, f64)>,
     target: String,
     tolerance: String,
     max_length: String,
-    results: Vec<Vec<f64>>,
+    results: Vec<Vec<(String, f64)>>,
     progress: f32,
     status: String,
     computing: bool,
     show_all: bool,
+    allow_repetition: bool,
+    max_uses: String,
     error: Option<String>,
-    cancel_sender: Option<mpsc::Sender<()>>,
-    shared_state: Arc<Mutex<(Vec<Vec<f64>>, f32, String, bool)>>,
+    stop_flag: Arc<AtomicBool>,
+    shared_state: Arc<Mutex<(Vec<Vec<(String, f64)>>, f32, String, bool)>>,
 }
 
 impl Default for Sum3App {
     fn default() -> Self {
         Self {
-            numbers: Vec::new(),
+            items: Vec::new(),
             target: "10.0".to_string(),
             tolerance: "0.1".to_string(),
             max_length: "5".to_string(),
@@ -30,8 +290,10 @@ impl Default for Sum3App {
             status: "就绪".to_string(),
             computing: false,
             show_all: false,
+            allow_repetition: false,
+            max_uses: String::new(),
             error: None,
-            cancel_sender: None,
+            stop_flag: Arc::new(AtomicBool::new(false)),
             shared_state: Arc::new(Mutex::new((Vec::new(), 0.0, "就绪".to_string(), false))),
         }
     }
@@ -50,7 +312,7 @@ impl eframe::App for Sum3App {
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("数字组合求解器");
-            
+
             // 错误显示
             if let Some(err) = &self.error {
                 ui.colored_label(egui::Color32::RED, err);
@@ -61,18 +323,12 @@ impl eframe::App for Sum3App {
                 if ui.button("导入数字文件").clicked() {
                     if let Some(path) = rfd::FileDialog::new().pick_file() {
                         let path_str = path.to_str().unwrap();
-                        let result = if path_str.ends_with(".csv") {
-                            read_numbers_from_csv(path_str)
-                        } else {
-                            read_numbers_from_txt(path_str)
-                        };
-                        
-                        match result {
-                            Ok(nums) => {
-                                self.numbers = nums;
-                                self.status = format!("已导入 {} 个数字", self.numbers.len());
+                        match load_labeled_items(path_str) {
+                            Ok(items) => {
+                                self.items = items;
+                                self.status = format!("已导入 {} 个数字", self.items.len());
                                 self.error = None;
-                                println!("成功导入 {} 个数字: {:?}", self.numbers.len(), self.numbers);
+                                println!("成功导入 {} 个数字: {:?}", self.items.len(), self.items);
                             }
                             Err(e) => {
                                 self.error = Some(format!("导入错误: {}", e));
@@ -81,7 +337,7 @@ impl eframe::App for Sum3App {
                         }
                     }
                 }
-                
+
                 ui.label(&self.status);
             });
 
@@ -104,6 +360,11 @@ impl eframe::App for Sum3App {
                     self.stop_computation();
                 }
                 ui.checkbox(&mut self.show_all, "显示所有解");
+                ui.checkbox(&mut self.allow_repetition, "允许重复使用数字");
+                if self.allow_repetition {
+                    ui.label("每个数字最多使用次数(留空不限，单值=统一上限，逗号分隔=按行独立上限):");
+                    ui.text_edit_singleline(&mut self.max_uses);
+                }
             });
 
             // 进度条
@@ -115,13 +376,7 @@ impl eframe::App for Sum3App {
                     if !self.show_all && i >= 1 {
                         break;
                     }
-                    let sum = res.iter().sum::<f64>();
-                    ui.label(format!(
-                        "解 {}: {:?} (总和: {:.2})",
-                        i + 1,
-                        res,
-                        sum
-                    ));
+                    ui.label(format_solution(i, res));
                 }
             });
         });
@@ -137,7 +392,7 @@ impl Sum3App {
                 return;
             }
         };
-        
+
         let tolerance = match self.tolerance.parse::<f64>() {
             Ok(t) => t,
             Err(_) => {
@@ -145,7 +400,7 @@ impl Sum3App {
                 return;
             }
         };
-        
+
         let max_length = match self.max_length.parse::<usize>() {
             Ok(m) => m,
             Err(_) => {
@@ -154,46 +409,75 @@ impl Sum3App {
             }
         };
 
-        if self.numbers.is_empty() {
+        if self.items.is_empty() {
             self.error = Some("请先导入数字".to_string());
             return;
         }
 
+        let max_uses = match parse_max_uses(&self.max_uses, self.items.len()) {
+            Ok(m) => m,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+
         self.computing = true;
         self.results.clear();
         self.progress = 0.0;
         self.status = "计算中...".to_string();
         self.error = None;
 
-        let numbers = self.numbers.clone();
+        let items = self.items.clone();
+        let allow_repetition = self.allow_repetition;
         let (tx, rx) = mpsc::channel();
-        let (cancel_tx, cancel_rx) = mpsc::channel();
 
-        // 保存取消通道以便停止计算
-        self.cancel_sender = Some(cancel_tx);
+        // 为本次计算分配新的停止标志，停止按钮通过它通知计算线程尽快退出
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        self.stop_flag = stop_flag.clone();
 
         let tx = Arc::new(Mutex::new(tx));
-        
+
         // 启动计算线程
-        let computation_thread = thread::spawn({
-            let numbers = numbers.clone();
+        thread::spawn({
+            let items = items.clone();
             let tx = tx.clone();
+            let stop_flag = stop_flag.clone();
+            let max_uses = max_uses.clone();
             move || {
                 let (progress_tx, progress_rx) = crossbeam_channel::unbounded();
                 let (result_tx, result_rx) = mpsc::channel();
-                
+
                 // 计算线程
                 thread::spawn({
-                    let numbers = numbers.clone();
+                    let items = items.clone();
+                    let stop_flag = stop_flag.clone();
+                    let max_uses = max_uses.clone();
                     move || {
                         println!("计算线程启动"); // 添加调试输出
-                        let results = find_combinations(
-                            &numbers,
-                            target,
-                            tolerance,
-                            Some(progress_tx),
-                            max_length,
-                        );
+                        let results = if allow_repetition {
+                            // 重复模式不关心具体是哪一行，标签直接取数值本身
+                            let values: Vec<f64> = items.iter().map(|(_, v)| *v).collect();
+                            label_by_value(find_combinations_with_repetition(
+                                &values,
+                                &max_uses,
+                                target,
+                                tolerance,
+                                max_length,
+                                1000,
+                                stop_flag,
+                            ))
+                        } else {
+                            find_combinations_labeled(
+                                &items,
+                                target,
+                                tolerance,
+                                Some(progress_tx),
+                                max_length,
+                                1000,
+                                stop_flag,
+                            )
+                        };
                         println!("计算完成，找到{}个解", results.len()); // 添加调试输出
                         result_tx.send(results).unwrap();
                     }
@@ -223,18 +507,11 @@ impl Sum3App {
             }
         });
 
-        // 取消监听线程
-        thread::spawn(move || {
-            if cancel_rx.recv().is_ok() {
-                computation_thread.thread().unpark();
-            }
-        });
-
         ctx.request_repaint_after(std::time::Duration::from_millis(100));
 
         enum ComputationMessage {
             Progress(f64),
-            Results(Vec<Vec<f64>>),
+            Results(Vec<Vec<(String, f64)>>),
         }
 
         // 使用App结构体中的共享状态
@@ -261,41 +538,17 @@ impl Sum3App {
     }
 
     fn stop_computation(&mut self) {
-        if let Some(sender) = self.cancel_sender.take() {
-            let _ = sender.send(());
-            self.computing = false;
-            self.status = "计算已停止".to_string();
-        }
+        self.stop_flag.store(true, Ordering::Relaxed);
+        self.computing = false;
+        self.status = "计算已停止".to_string();
     }
 }
 
 fn main() {
-    // 添加命令行参数支持
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 && args[1] == "--test" {
-        // 命令行测试模式
-        println!("运行命令行测试模式...");
-        let numbers = vec![4.5, 5.5, 6.0, 7.5, 9.0, 10.5, 12.0];
-        let target = 15.0;
-        let tolerance = 0.1;
-        let max_length = 5;
-        
-        println!("测试数据: {:?}", numbers);
-        println!("目标值: {}, 误差: {}, 最大长度: {}", target, tolerance, max_length);
-        
-        let results = sum3_solver::find_combinations(
-            &numbers,
-            target,
-            tolerance,
-            None,
-            max_length,
-        );
-        
-        println!("找到 {} 个解:", results.len());
-        for (i, res) in results.iter().enumerate() {
-            let sum = res.iter().sum::<f64>();
-            println!("解 {}: {:?} (总和: {:.2})", i + 1, res, sum);
-        }
+    let cli = Cli::parse();
+
+    if let Some(Command::Batch(args)) = cli.command {
+        run_batch(args);
         return;
     }
 
@@ -315,7 +568,7 @@ fn main() {
                 .get_mut(&egui::FontFamily::Proportional)
                 .unwrap()
                 .insert(0, "noto_serif_cjk_sc".to_owned());
-            
+
             let mut style = egui::Style::default();
             style.text_styles.insert(
                 egui::TextStyle::Heading,
@@ -325,10 +578,10 @@ fn main() {
                 egui::TextStyle::Body,
                 egui::FontId::new(16.0, egui::FontFamily::Name("noto_serif_cjk_sc".into())),
             );
-            
+
             cc.egui_ctx.set_fonts(fonts);
             cc.egui_ctx.set_style(style);
-            
+
             Ok(Box::new(Sum3App::default()))
         }),
     ) {